@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use toml;
+
+pub const CONFIG_FILE_NAME: &'static str = "tzbuildasset.toml";
+
+#[derive(RustcDecodable, Default, Debug)]
+pub struct Profile {
+    pub trainzutil_path: Option<String>,
+}
+
+#[derive(RustcDecodable, Default, Debug)]
+pub struct Config {
+    pub trainzutil_path: Option<String>,
+    pub temp_dir: Option<String>,
+    pub recursive: Option<bool>,
+    pub show_config: Option<bool>,
+    pub show_kuid: Option<bool>,
+    pub profiles: Option<HashMap<String, Profile>>,
+}
+
+impl Config {
+    pub fn trainzutil_path_for(&self, profile: Option<&str>) -> Option<String> {
+        let from_profile = profile.and_then(|name| {
+            let profiles = self.profiles.as_ref();
+            if !profiles.map_or(false, |profiles| profiles.contains_key(name)) {
+                ::log::log(::log::Mode::Normal, ::log::Severity::Warn,
+                        format_args!("profile '{}' not found in config", name));
+            }
+            profiles.and_then(|profiles| profiles.get(name))
+                    .and_then(|profile| profile.trainzutil_path.clone())
+        });
+        from_profile.or_else(|| self.trainzutil_path.clone())
+    }
+}
+
+pub fn discover(start: &Path) -> Option<PathBuf> {
+    let mut dir = Some(start);
+    while let Some(current) = dir {
+        let candidate = current.join(CONFIG_FILE_NAME);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        dir = current.parent();
+    }
+    None
+}
+
+pub fn load(path: &Path) -> Result<Config, String> {
+    let mut contents = String::new();
+    try!(File::open(path)
+            .and_then(|mut file| file.read_to_string(&mut contents))
+            .map_err(|e| format!("unable to read {}: {}", path.display(), e)));
+
+    toml::decode_str(&contents)
+            .ok_or_else(|| format!("invalid config file: {}", path.display()))
+}
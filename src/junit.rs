@@ -0,0 +1,70 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub struct Failure {
+    pub kind: &'static str,
+    pub message: String,
+}
+
+pub struct TestCase {
+    pub name: String,
+    pub kuid: String,
+    pub failures: Vec<Failure>,
+}
+
+impl TestCase {
+    fn is_failed(&self) -> bool {
+        self.failures.iter().any(|f| f.kind == "error")
+    }
+}
+
+pub struct TestSuite {
+    pub name: String,
+    pub cases: Vec<TestCase>,
+}
+
+impl TestSuite {
+    fn failure_count(&self) -> usize {
+        self.cases.iter().filter(|case| case.is_failed()).count()
+    }
+}
+
+pub fn write_report(path: &Path, suite: &TestSuite) -> io::Result<()> {
+    let mut file = try!(File::create(path));
+
+    try!(write!(file, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n"));
+    try!(write!(file, "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\">\n",
+            escape(&suite.name), suite.cases.len(), suite.failure_count()));
+
+    for case in &suite.cases {
+        if case.failures.is_empty() {
+            try!(write!(file, "  <testcase name=\"{}\" classname=\"{}\"/>\n",
+                    escape(&case.name), escape(&case.kuid)));
+        } else {
+            try!(write!(file, "  <testcase name=\"{}\" classname=\"{}\">\n",
+                    escape(&case.name), escape(&case.kuid)));
+            for failure in &case.failures {
+                if failure.kind == "error" {
+                    try!(write!(file, "    <failure type=\"{}\" message=\"{}\"/>\n",
+                            failure.kind, escape(&failure.message)));
+                } else {
+                    try!(write!(file, "    <system-out>{}: {}</system-out>\n",
+                            failure.kind, escape(&failure.message)));
+                }
+            }
+            try!(write!(file, "  </testcase>\n"));
+        }
+    }
+
+    try!(write!(file, "</testsuite>\n"));
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -1,6 +1,8 @@
 use std::fmt;
 use std::sync::{Mutex};
 
+use rustc_serialize::json;
+
 lazy_static! {
     static ref LOGGER: Mutex<Option<Logger>> = Mutex::new(None);
 }
@@ -21,7 +23,23 @@ impl Mode {
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum Severity { Error, Warn, Info }
 
-#[derive(Copy, Clone, Default, Debug)]
+impl Severity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warn => "warn",
+            Severity::Info => "info",
+        }
+    }
+}
+
+/// Output encoding for log records. `Text` is the historical human-readable
+/// format; `Json` serializes each record so CI tooling can parse results
+/// without scraping `ERROR`/`WARN` prefixed lines.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Format { Text, Json }
+
+#[derive(Copy, Clone, Default, Debug, RustcEncodable)]
 pub struct Statistics {
     pub errors: u32,
     pub warnings: u32,
@@ -29,32 +47,78 @@ pub struct Statistics {
 
 struct Logger {
     pub mode: Mode,
+    pub format: Format,
     pub stats: Statistics,
 }
 
-pub fn init(mode: Mode) {
+pub fn init(mode: Mode, format: Format) {
     *LOGGER.lock().unwrap() = Some(Logger {
         mode: mode,
+        format: format,
         stats: Statistics::default(),
     });
 }
 
+#[derive(RustcEncodable)]
+struct LogRecord<'a> {
+    severity: &'static str,
+    asset: Option<&'a str>,
+    message: String,
+}
+
 pub fn log(mode: Mode, severity: Severity, args: fmt::Arguments) {
+    log_record(mode, severity, None, args, true)
+}
+
+/// Like `log`, but attaches an `asset` KUID/path to the record so JSON output
+/// can carry it as a field instead of it being baked into the message text.
+/// Callers that already logged the same event through `log` (to target a
+/// different `Mode`) should not double it up in `Statistics`, so this does
+/// not count towards it. Under `Format::Json` the record always emits
+/// regardless of `mode`, since `--format json` alone should deliver the
+/// per-asset drill-down without also requiring `--silent`.
+pub fn log_asset(mode: Mode, severity: Severity, asset: &str, args: fmt::Arguments) {
+    log_record(mode, severity, Some(asset), args, false)
+}
+
+fn log_record(mode: Mode, severity: Severity, asset: Option<&str>, args: fmt::Arguments, count: bool) {
     if let Some(ref mut logger) = *LOGGER.lock().unwrap() {
-        match severity {
-            Severity::Error => logger.stats.errors += 1,
-            Severity::Warn => logger.stats.warnings += 1,
-            _ => ()
+        if count {
+            match severity {
+                Severity::Error => logger.stats.errors += 1,
+                Severity::Warn => logger.stats.warnings += 1,
+                _ => ()
+            }
         }
 
-        if logger.mode.accepts(mode) {
-            if logger.mode == Mode::Silent {
-                print!("{}\n", args);
-            } else {
-                match severity {
-                    Severity::Error => print!("ERROR {}\n", args),
-                    Severity::Warn  => print!("WARN  {}\n", args),
-                    Severity::Info  => print!("INFO  {}\n", args),
+        let emit = (logger.format == Format::Json && asset.is_some()) || logger.mode.accepts(mode);
+        if emit {
+            match logger.format {
+                Format::Json => {
+                    let record = LogRecord {
+                        severity: severity.as_str(),
+                        asset: asset,
+                        message: format!("{}", args),
+                    };
+                    print!("{}\n", json::encode(&record).unwrap());
+                }
+                Format::Text => {
+                    if logger.mode == Mode::Silent {
+                        match asset {
+                            Some(asset) => print!("{} : {}\n", asset, args),
+                            None => print!("{}\n", args),
+                        }
+                    } else {
+                        let prefix = match severity {
+                            Severity::Error => "ERROR",
+                            Severity::Warn  => "WARN ",
+                            Severity::Info  => "INFO ",
+                        };
+                        match asset {
+                            Some(asset) => print!("{} {} : {}\n", prefix, asset, args),
+                            None => print!("{} {}\n", prefix, args),
+                        }
+                    }
                 }
             }
         }
@@ -83,3 +147,41 @@ pub fn statistics() -> Statistics {
     let logger: &Option<Logger> = &*LOGGER.lock().unwrap();
     logger.as_ref().unwrap().stats.clone()
 }
+
+#[derive(RustcEncodable)]
+struct AssetResult<'a> {
+    kuid: &'a str,
+    name: &'a str,
+    succeeded: bool,
+}
+
+#[derive(RustcEncodable)]
+struct BuildSummary<'a> {
+    total: usize,
+    succeeded: usize,
+    failed: usize,
+    statistics: Statistics,
+    assets: Vec<AssetResult<'a>>,
+}
+
+/// Emits the final, machine-readable build summary when running with
+/// `Format::Json`. In `Format::Text` this is a no-op since the human summary
+/// is already printed by `build` via `log_normal!`/`log_silent!`.
+pub fn log_summary(total: usize, succeeded: usize, assets: &[(String, String, bool)]) {
+    if let Some(ref logger) = *LOGGER.lock().unwrap() {
+        if logger.format == Format::Json {
+            let summary = BuildSummary {
+                total: total,
+                succeeded: succeeded,
+                failed: total - succeeded,
+                statistics: logger.stats,
+                assets: assets.iter().map(|&(ref kuid, ref name, succeeded)| AssetResult {
+                    kuid: kuid,
+                    name: name,
+                    succeeded: succeeded,
+                }).collect(),
+            };
+            print!("{}\n", json::encode(&summary).unwrap());
+        }
+    }
+}
@@ -1,7 +1,12 @@
 extern crate docopt;
+extern crate flate2;
 #[macro_use] extern crate lazy_static;
+extern crate num_cpus;
 extern crate regex;
 extern crate rustc_serialize;
+extern crate tar;
+extern crate toml;
+extern crate xz2;
 
 use std::env;
 use std::error::{Error};
@@ -11,16 +16,23 @@ use std::ffi::{OsString};
 use std::fs::{self, File};
 use std::path::{self, Path, PathBuf};
 use std::process::{self};
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::thread;
 
 use docopt::{Docopt};
 
 use regex::{Regex};
 
 use displayprefix::{with_prefix};
+use trainzutil::{ResultExt};
 
 
+mod config;
 mod displayprefix;
+mod junit;
 #[macro_use] mod log;
+mod package;
 mod trainzutil;
 
 
@@ -51,6 +63,13 @@ Options:
   -v --verbose         Detailed output
   -s --silent          Silent output
   --temp-dir PATH      Use specified temporary directory
+  --jobs N             Number of assets to validate concurrently; 0 uses the CPU count [default: 0]
+  --format FORMAT      Output format: text or json [default: text]
+  --report PATH        Write a JUnit XML report of validation results to PATH
+  --profile NAME       Use the named TrainzUtil profile from tzbuildasset.toml
+  --package PATH       Archive each successfully validated asset into PATH
+  --compression CODEC  Archive codec: gzip or xz [default: gzip]
+  --xz-dict-size BYTES  Dictionary/window size for the xz codec; 0 uses 64 MiB [default: 0]
   -h --help            Show help
   --version            Show version
 
@@ -70,6 +89,13 @@ struct Args {
     flag_verbose: bool,
     flag_silent: bool,
     flag_temp_dir: Option<String>,
+    flag_jobs: usize,
+    flag_format: String,
+    flag_report: Option<String>,
+    flag_profile: Option<String>,
+    flag_package: Option<String>,
+    flag_compression: String,
+    flag_xz_dict_size: u32,
     arg_INPUT: Option<String>,
 
     flag_help: bool,
@@ -86,22 +112,53 @@ fn main() {
         (_, true) => log::Mode::Verbose,
         (_, _) => log::Mode::Normal,
     };
-    log::init(log_mode);
+    let log_format = match args.flag_format.to_lowercase().as_ref() {
+        "json" => log::Format::Json,
+        _ => log::Format::Text,
+    };
+    log::init(log_mode, log_format);
 
     if args.flag_version {
         println!("{}", env!("CARGO_PKG_VERSION"));
         return;
     }
     else {
+        let build_path = env::current_dir().unwrap().join(args.arg_INPUT.unwrap_or(String::new()));
+
+        let config = config::discover(&build_path)
+                .and_then(|path| config::load(&path).map_err(|e| log_normal!(Error, "{}", e)).ok())
+                .unwrap_or_default();
+
+        let trainzutil_path = args.flag_trainzutil
+                .or_else(|| config.trainzutil_path_for(args.flag_profile.as_ref().map(String::as_ref)))
+                .map(OsString::from)
+                .or_else(|| env::var_os("TRAINZUTIL_PATH"))
+                .unwrap_or_else(|| OsString::from("TrainzUtil"));
+
+        let temp_dir = args.flag_temp_dir.or(config.temp_dir);
+
+        let codec = match args.flag_compression.to_lowercase().as_ref() {
+            "xz" => package::Codec::Xz {
+                dict_size: if args.flag_xz_dict_size == 0 {
+                    package::DEFAULT_XZ_DICT_SIZE
+                } else {
+                    args.flag_xz_dict_size
+                },
+            },
+            _ => package::Codec::Gzip,
+        };
+
         let success = build(&BuildArguments {
-            build_path: &env::current_dir().unwrap().join(args.arg_INPUT.unwrap_or(String::new())),
-            trainzutil_path: Path::new(&args.flag_trainzutil.map(|s| OsString::from(s))
-                    .or_else(|| env::var_os("TRAINZUTIL_PATH"))
-                    .unwrap_or_else(|| OsString::from("TrainzUtil"))),
-            temp_path: args.flag_temp_dir.as_ref().map(|s| Path::new(s)),
-            show_config_path: args.flag_config,
-            show_kuid: args.flag_kuid,
-            recursive: args.flag_recursive
+            build_path: &build_path,
+            trainzutil_path: Path::new(&trainzutil_path),
+            temp_path: temp_dir.as_ref().map(|s| Path::new(s)),
+            show_config_path: args.flag_config || config.show_config.unwrap_or(false),
+            show_kuid: args.flag_kuid || config.show_kuid.unwrap_or(false),
+            recursive: args.flag_recursive || config.recursive.unwrap_or(false),
+            jobs: if args.flag_jobs == 0 { num_cpus::get() } else { args.flag_jobs },
+            report_path: args.flag_report.as_ref().map(|s| Path::new(s)),
+            package_path: args.flag_package.as_ref().map(|s| Path::new(s)),
+            package_codec: codec,
         });
 
         process::exit(if success { 0 } else { 1 });
@@ -115,7 +172,11 @@ struct BuildArguments<'a> {
     pub temp_path: Option<&'a Path>,
     pub show_config_path: bool,
     pub show_kuid: bool,
-    pub recursive: bool
+    pub recursive: bool,
+    pub jobs: usize,
+    pub report_path: Option<&'a Path>,
+    pub package_path: Option<&'a Path>,
+    pub package_codec: package::Codec,
 }
 
 
@@ -123,6 +184,7 @@ fn build(args: &BuildArguments) -> bool {
 
     log_verbose!(Info, "Build path: {}", args.build_path.display());
     log_verbose!(Info, "TrainzUtil path: {}", args.trainzutil_path.display());
+    log_verbose!(Info, "Validation jobs: {}", args.jobs);
 
     match trainzutil::execute(args.trainzutil_path, &["version"]) {
         Ok(output) => log_verbose!(Info, "TrainzUtil version: {}", output.lines[0]),
@@ -135,19 +197,33 @@ fn build(args: &BuildArguments) -> bool {
 
     let assets = locate_assets(args.build_path, args.recursive);
     let mut installed = Vec::with_capacity(assets.len());
-    let mut succeeded_count = 0usize;
-
-    for asset in &assets {
-        if install_asset(asset, args) {
-            installed.push(asset);
+    let mut installed_indices = Vec::with_capacity(assets.len());
+    let mut outcomes: Vec<AssetOutcome> = assets.iter().map(|_| {
+        AssetOutcome { succeeded: false, failures: Vec::new() }
+    }).collect();
+
+    for (index, asset) in assets.iter().enumerate() {
+        match install_asset(asset, args) {
+            Ok(()) => {
+                installed.push(asset);
+                installed_indices.push(index);
+            }
+            Err(message) => {
+                outcomes[index].failures.push(junit::Failure { kind: "error", message: message });
+            }
         }
     }
 
-    for asset in &installed {
-        if validate_asset(asset, args) {
-            succeeded_count += 1;
-        }
+    let validated: Vec<AssetOutcome> = if args.jobs > 1 && installed.len() > 1 {
+        validate_assets_parallel(&installed, args)
+    } else {
+        installed.iter().map(|asset| validate_asset(asset, args)).collect()
+    };
+
+    for (&index, outcome) in installed_indices.iter().zip(validated.into_iter()) {
+        outcomes[index] = outcome;
     }
+    let succeeded_count = outcomes.iter().filter(|outcome| outcome.succeeded).count();
 
     let output = format!("BUILD {} ({} Total, {} Succeeded, {} Failed)",
             if succeeded_count == assets.len() { "SUCCESS" } else { "FAILED " },
@@ -161,6 +237,31 @@ fn build(args: &BuildArguments) -> bool {
     log_normal!(Info, "{}", line);
     log_silent!(Info, "OK ({} Errors, {} Warnings)", assets.len() - succeeded_count, 0);
 
+    let asset_results: Vec<(String, String, bool)> = assets.iter().zip(outcomes.iter())
+            .map(|(asset, outcome)| (asset.kuid.clone(), asset.name.clone(), outcome.succeeded))
+            .collect();
+    log::log_summary(assets.len(), succeeded_count, &asset_results);
+
+    if let Some(package_path) = args.package_path {
+        package_assets(package_path, &assets, &outcomes, args);
+    }
+
+    if let Some(report_path) = args.report_path {
+        let suite = junit::TestSuite {
+            name: "tzbuildasset".to_owned(),
+            cases: assets.iter().zip(outcomes.into_iter()).map(|(asset, outcome)| {
+                junit::TestCase {
+                    name: asset.name.clone(),
+                    kuid: asset.kuid.clone(),
+                    failures: outcome.failures,
+                }
+            }).collect(),
+        };
+        if let Err(e) = junit::write_report(report_path, &suite) {
+            log_normal!(Error, "Unable to write report to {}: {}", report_path.display(), e);
+        }
+    }
+
     assets.len() == succeeded_count
 }
 
@@ -227,7 +328,7 @@ fn locate_assets_recursive(path: &Path, recursive: bool, located_assets: &mut Ve
 }
 
 
-fn install_asset(asset: &Asset, args: &BuildArguments) -> bool {
+fn install_asset(asset: &Asset, args: &BuildArguments) -> Result<(), String> {
 
     let asset_path: &Path = &asset.path;
     let asset_kuid: &str = &asset.kuid;
@@ -237,40 +338,112 @@ fn install_asset(asset: &Asset, args: &BuildArguments) -> bool {
 
     let result = ({
         match trainzutil::execute(args.trainzutil_path,
-                &["installfrompath", asset_path.to_string_lossy().as_ref()]) {
+                &["installfrompath", asset_path.to_string_lossy().as_ref()])
+                .context("installfrompath", Some(asset_kuid)) {
             Ok(output) => {
                 log_verbose!(Info, "Install susccess:\n{}", with_prefix(">", &output)); Ok(())
             },
             Err(e) => {
-                log_normal!(Error, "Install failed: {}", e); Err(())
+                log_normal!(Error, "Install failed: {}", e); Err(format!("{}", e))
             }
         }
     }).and_then(|_| {
-        match trainzutil::execute(args.trainzutil_path, &["commit", asset_kuid]) {
+        match trainzutil::execute(args.trainzutil_path, &["commit", asset_kuid])
+                .context("commit", Some(asset_kuid)) {
             Ok(output) => {
                 log_verbose!(Info, "Commit success:\n{}", with_prefix(">", &output)); Ok(())
             },
             Err(e) => {
-                log_normal!(Error, "Commit failed: {}", e); Err(())
+                log_normal!(Error, "Commit failed: {}", e); Err(format!("{}", e))
             }
         }
     }).and_then(|_| {
         log_verbose!(Info, "Install success");
         Ok(())
-    }).or_else(|_| {
+    }).or_else(|message| {
         log_normal!(Error, "Install failed");
-        Err(())
+        Err(message)
     });
 
-    result.is_ok()
+    result
+}
+
+
+/// Outcome of validating a single asset: whether it passed, plus the
+/// classified diagnostic lines used to build the JUnit report.
+struct AssetOutcome {
+    succeeded: bool,
+    failures: Vec<junit::Failure>,
 }
 
+fn validate_asset(asset: &Asset, args: &BuildArguments) -> AssetOutcome {
+    log_normal!(Info, "Validating asset '{}'", asset.name);
+    let result = trainzutil::execute(args.trainzutil_path, &["validate", &asset.kuid])
+            .context("validate", Some(&asset.kuid));
+    apply_validation_result(asset, args, result)
+}
+
+/// Runs the validation phase over `installed` using a bounded pool of `args.jobs`
+/// worker threads, each calling `trainzutil::execute` independently. The
+/// `installfrompath`/`commit` phase is not parallelized here since it mutates the
+/// shared asset database, but `validate` is read-only so workers can run it
+/// concurrently. Results are collected through a channel and applied back on the
+/// calling thread, in asset order, so `log_validation_output` and `Statistics`
+/// stay deterministic regardless of which worker finished first.
+fn validate_assets_parallel(installed: &[&Asset], args: &BuildArguments) -> Vec<AssetOutcome> {
+    let worker_count = args.jobs.min(installed.len());
+    let trainzutil_path = Arc::new(args.trainzutil_path.to_owned());
+    let next_index = Arc::new(Mutex::new(0usize));
+    let kuids: Arc<Vec<String>> = Arc::new(installed.iter().map(|a| a.kuid.clone()).collect());
+    let (tx, rx) = mpsc::channel();
+
+    let workers: Vec<_> = (0..worker_count).map(|_| {
+        let trainzutil_path = trainzutil_path.clone();
+        let next_index = next_index.clone();
+        let kuids = kuids.clone();
+        let tx = tx.clone();
+
+        thread::spawn(move || {
+            loop {
+                let index = {
+                    let mut next_index = next_index.lock().unwrap();
+                    if *next_index >= kuids.len() {
+                        break;
+                    }
+                    let index = *next_index;
+                    *next_index += 1;
+                    index
+                };
+
+                let result = trainzutil::execute(&trainzutil_path, &["validate", &kuids[index]])
+                        .context("validate", Some(&kuids[index]));
+                if tx.send((index, result)).is_err() {
+                    break;
+                }
+            }
+        })
+    }).collect();
+    drop(tx);
 
-fn validate_asset(asset: &Asset, args: &BuildArguments) -> bool {
+    let mut results: Vec<Option<trainzutil::Result>> = (0..installed.len()).map(|_| None).collect();
+    for (index, result) in rx {
+        results[index] = Some(result);
+    }
+
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    installed.iter().zip(results).map(|(asset, result)| {
+        log_normal!(Info, "Validating asset '{}'", asset.name);
+        apply_validation_result(asset, args, result.unwrap())
+    }).collect()
+}
+
+fn apply_validation_result(asset: &Asset, args: &BuildArguments, result: trainzutil::Result) -> AssetOutcome {
 
     let asset_path: &Path = &asset.path;
     let asset_kuid: &str = &asset.kuid;
-    let asset_name: &str  = &asset.name;
 
     let asset_relative_path = &{
         let comps = asset_path.components().skip(args.build_path.components().count());
@@ -289,13 +462,13 @@ fn validate_asset(asset: &Asset, args: &BuildArguments) -> bool {
         (_, _)    => format!("[{}]", asset_relative_path.to_string_lossy().as_ref()),
     };
 
-    log_normal!(Info, "Validating asset '{}'", asset_name);
+    let mut failures = Vec::new();
 
     let result = ({
-        match trainzutil::execute(args.trainzutil_path, &["validate", asset_kuid]) {
+        match result {
             Ok(output) => {
                 log_verbose!(Info, "Validation success:\n{}", with_prefix(">", &output));
-                log_validation_output(&asset_output_name, &output);
+                failures = log_validation_output(&asset_output_name, &output);
                 if output.errors == 0 {
                     Ok(())
                 } else {
@@ -303,7 +476,9 @@ fn validate_asset(asset: &Asset, args: &BuildArguments) -> bool {
                 }
             }
             Err(e) => {
-                log_normal!(Error, "Validation failed: {}", e); Err(())
+                log_normal!(Error, "Validation failed: {}", e);
+                failures.push(junit::Failure { kind: "error", message: format!("validate: {}", e) });
+                Err(())
             }
         }
     }).and_then(|_| {
@@ -314,11 +489,40 @@ fn validate_asset(asset: &Asset, args: &BuildArguments) -> bool {
         Err(())
     });
 
-    result.is_ok()
+    AssetOutcome { succeeded: result.is_ok(), failures: failures }
+}
+
+
+/// Bundles the `installed`+succeeded subset that `build` already tracks into
+/// compressed archives under `package_path`, keyed by each asset's KUID and
+/// username. Runs only once an asset has validated with zero errors.
+fn package_assets(package_path: &Path, assets: &[Asset], outcomes: &[AssetOutcome], args: &BuildArguments) {
+    if let Err(e) = fs::create_dir_all(package_path) {
+        log_normal!(Error, "Unable to create package directory {}: {}", package_path.display(), e);
+        return;
+    }
+
+    log_verbose!(Info, "Packaging with codec {:?}", args.package_codec);
+
+    for (asset, outcome) in assets.iter().zip(outcomes.iter()) {
+        if !outcome.succeeded {
+            continue;
+        }
+
+        let archive_name = package::archive_file_name(&asset.kuid, &asset.name, args.package_codec);
+        let archive_path = package_path.join(&archive_name);
+
+        log_normal!(Info, "Packaging asset '{}'", asset.name);
+        match package::package_asset(&asset.path, &archive_path, args.package_codec) {
+            Ok(()) => log_verbose!(Info, "Packaged '{}' to {}", asset.name, archive_path.display()),
+            Err(e) => log_normal!(Error, "Packaging failed for '{}': {}", asset.name, e),
+        }
+    }
 }
 
+fn log_validation_output(asset: &str, output: &trainzutil::Output) -> Vec<junit::Failure> {
+    let mut failures = Vec::new();
 
-fn log_validation_output(asset: &str, output: &trainzutil::Output) {
     for line in &output.lines {
         if let Some(caps) = trainzutil::TZUTIL_OUTPUT_MATCHER.captures(line) {
             let prefix = caps.name("prefix").unwrap();
@@ -331,12 +535,19 @@ fn log_validation_output(asset: &str, output: &trainzutil::Output) {
                  _   => unreachable!()
             }
             match prefix {
-                "-" => log_silent!(Error, "{} {} : {}", prefix, asset, message),
-                "!" => log_silent!( Warn, "{} {} : {}", prefix, asset, message),
-                "+" => log_silent!( Info, "{} {} : {}", prefix, asset, message),
-                ";" => log_silent!( Info, "{} {} : {}", prefix, asset, message),
+                "-" => log::log_asset(log::Mode::Silent, log::Severity::Error, asset, format_args!("{} {}", prefix, message)),
+                "!" => log::log_asset(log::Mode::Silent, log::Severity::Warn,  asset, format_args!("{} {}", prefix, message)),
+                "+" => log::log_asset(log::Mode::Silent, log::Severity::Info,  asset, format_args!("{} {}", prefix, message)),
+                ";" => log::log_asset(log::Mode::Silent, log::Severity::Info,  asset, format_args!("{} {}", prefix, message)),
                  _   => unreachable!()
             }
+            match prefix {
+                "-" => failures.push(junit::Failure { kind: "error", message: message.to_owned() }),
+                "!" => failures.push(junit::Failure { kind: "warning", message: message.to_owned() }),
+                _ => ()
+            }
         }
     }
+
+    failures
 }
@@ -0,0 +1,82 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use tar::Builder;
+use xz2::stream::{Check, Filters, LzmaOptions, Stream};
+use xz2::write::XzEncoder;
+
+pub const DEFAULT_XZ_DICT_SIZE: u32 = 64 * 1024 * 1024;
+
+#[derive(Copy, Clone, Debug)]
+pub enum Codec {
+    Gzip,
+    Xz { dict_size: u32 },
+}
+
+impl Codec {
+    fn extension(self) -> &'static str {
+        match self {
+            Codec::Gzip => "tar.gz",
+            Codec::Xz { .. } => "tar.xz",
+        }
+    }
+}
+
+pub fn archive_file_name(kuid: &str, username: &str, codec: Codec) -> String {
+    let sanitize = |s: &str| -> String {
+        s.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' }).collect()
+    };
+    format!("{}_{}.{}", sanitize(kuid), sanitize(username), codec.extension())
+}
+
+pub fn package_asset(asset_path: &Path, archive_path: &Path, codec: Codec) -> io::Result<()> {
+    let file = try!(File::create(archive_path));
+
+    match codec {
+        Codec::Gzip => {
+            let encoder = GzEncoder::new(file, Compression::Best);
+            write_archive(encoder, asset_path)
+        }
+        Codec::Xz { dict_size } => {
+            let mut options = try!(LzmaOptions::new_preset(9));
+            options.dict_size(dict_size);
+
+            let mut filters = Filters::new();
+            filters.lzma2(&options);
+
+            let stream = try!(Stream::new_stream_encoder(&filters, Check::Crc64));
+            let encoder = XzEncoder::new_stream(file, stream);
+            write_archive(encoder, asset_path)
+        }
+    }
+}
+
+// GzEncoder/XzEncoder's Drop impls discard the result of their final flush,
+// so finishing has to happen explicitly to catch a late I/O error.
+trait FinishWrite: Write {
+    fn finish_write(self) -> io::Result<()>;
+}
+
+impl FinishWrite for GzEncoder<File> {
+    fn finish_write(self) -> io::Result<()> {
+        try!(self.finish());
+        Ok(())
+    }
+}
+
+impl FinishWrite for XzEncoder<File> {
+    fn finish_write(self) -> io::Result<()> {
+        try!(self.finish());
+        Ok(())
+    }
+}
+
+fn write_archive<W: FinishWrite>(writer: W, asset_path: &Path) -> io::Result<()> {
+    let mut builder = Builder::new(writer);
+    try!(builder.append_dir_all(".", asset_path));
+    let encoder = try!(builder.into_inner());
+    encoder.finish_write()
+}
@@ -73,7 +73,28 @@ impl fmt::Display for self::Output {
 pub enum Error {
     Failure(self::Output),
     NotFound,
-    Unknown(Box<error::Error>)
+    Unknown(Box<error::Error + Send + Sync>),
+    Context { operation: String, kuid: Option<String>, source: Box<Error> }
+}
+
+impl Error {
+    pub fn context<S: Into<String>>(self, operation: S, kuid: Option<&str>) -> Error {
+        Error::Context {
+            operation: operation.into(),
+            kuid: kuid.map(|k| k.to_owned()),
+            source: Box::new(self),
+        }
+    }
+}
+
+pub trait ResultExt<T> {
+    fn context<S: Into<String>>(self, operation: S, kuid: Option<&str>) -> result::Result<T, Error>;
+}
+
+impl<T> ResultExt<T> for result::Result<T, Error> {
+    fn context<S: Into<String>>(self, operation: S, kuid: Option<&str>) -> result::Result<T, Error> {
+        self.map_err(|e| e.context(operation, kuid))
+    }
 }
 
 impl error::Error for Error {
@@ -82,12 +103,14 @@ impl error::Error for Error {
             Error::Failure(_) => "TrainzUtil command failed",
             Error::NotFound => "TrainzUtil not found",
             Error::Unknown(_) => "unknown error",
+            Error::Context { .. } => "TrainzUtil invocation failed",
         }
     }
 
     fn cause(&self) -> Option<&error::Error> {
         match *self {
             Error::Unknown(ref e) => Some(&**e),
+            Error::Context { ref source, .. } => Some(&**source),
             _ => None
         }
     }
@@ -99,7 +122,14 @@ impl fmt::Display for Error {
             Error::Failure(ref output) =>
                 write!(f, "TrainzUtil command failed with following output:\n{}", with_prefix(">", output)),
             Error::NotFound => write!(f, "TrainzUtil executable was not found"),
-            Error::Unknown(ref e) => write!(f, "Unknown error: {}", e)
+            Error::Unknown(ref e) => write!(f, "Unknown error: {}", e),
+            Error::Context { ref operation, ref kuid, ref source } => {
+                match *kuid {
+                    Some(ref kuid) => try!(write!(f, "{} <{}>: ", operation, kuid)),
+                    None => try!(write!(f, "{}: ", operation)),
+                }
+                write!(f, "{}", source)
+            }
         }
     }
 }